@@ -0,0 +1,443 @@
+//! Neural-network autopilot for `Ship`, plus a headless genetic-algorithm
+//! trainer that breeds better pilots across generations.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand::{prelude::SmallRng, Rng, SeedableRng};
+
+use crate::sensors::{self, RaycastSensors};
+use crate::{Owner, SteeringControl, ThrustEngine, Velocity, Weapon};
+
+/// Inputs to the brain: ship velocity (2) plus one distance per ray.
+const N_INPUTS: usize = 2 + sensors::N_RAYS;
+const N_OUTPUTS: usize = 4;
+const HIDDEN_LAYER: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Activation {
+    ReLU,
+    Sigmoid,
+    Tanh,
+}
+
+impl Activation {
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            Activation::ReLU => x.max(0.0),
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            Activation::Tanh => x.tanh(),
+        }
+    }
+}
+
+/// A small feed-forward network. `weights[layer][row]` holds one neuron's
+/// weights, with the last entry in the row the bias (the activation vector
+/// is extended with a constant `1.0` before each matrix multiply).
+#[derive(Debug, Clone)]
+pub(crate) struct Brain {
+    layers: Vec<usize>,
+    weights: Vec<Vec<Vec<f32>>>,
+    activation: Activation,
+}
+
+impl Brain {
+    pub(crate) fn new(layers: Vec<usize>, activation: Activation, rng: &mut impl Rng) -> Self {
+        let weights = layers
+            .windows(2)
+            .map(|pair| {
+                let (prev, next) = (pair[0], pair[1]);
+                (0..next)
+                    .map(|_| (0..prev + 1).map(|_| rng.gen_range(-1.0..1.0)).collect())
+                    .collect()
+            })
+            .collect();
+        Self {
+            layers,
+            weights,
+            activation,
+        }
+    }
+
+    pub(crate) fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        let mut activations = inputs.to_vec();
+        for layer in &self.weights {
+            let mut with_bias = activations;
+            with_bias.push(1.0);
+            activations = layer
+                .iter()
+                .map(|row| {
+                    let sum: f32 = row.iter().zip(&with_bias).map(|(w, a)| w * a).sum();
+                    self.activation.apply(sum)
+                })
+                .collect();
+        }
+        activations
+    }
+
+    /// Breeds a child from two parents: each weight is independently taken
+    /// from parent `a`, parent `b`, or their average.
+    pub(crate) fn crossover(a: &Brain, b: &Brain, rng: &mut impl Rng) -> Brain {
+        let weights = a
+            .weights
+            .iter()
+            .zip(&b.weights)
+            .map(|(layer_a, layer_b)| {
+                layer_a
+                    .iter()
+                    .zip(layer_b)
+                    .map(|(row_a, row_b)| {
+                        row_a
+                            .iter()
+                            .zip(row_b)
+                            .map(|(wa, wb)| match rng.gen_range(0..3) {
+                                0 => *wa,
+                                1 => *wb,
+                                _ => (wa + wb) / 2.0,
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        Brain {
+            layers: a.layers.clone(),
+            weights,
+            activation: a.activation,
+        }
+    }
+
+    /// Nudges each weight by a standard-normal sample scaled by `mut_rate`,
+    /// with small probability per weight.
+    pub(crate) fn mutate(&mut self, mut_rate: f32, rng: &mut impl Rng) {
+        for layer in &mut self.weights {
+            for row in layer {
+                for w in row {
+                    if rng.gen_bool(0.1) {
+                        *w += standard_normal(rng) * mut_rate;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Box-Muller transform; avoids pulling in a `rand_distr` dependency for one
+/// distribution.
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Drives `SteeringControl`, `ThrustEngine` and `Weapon` instead of the
+/// keyboard, using the ship's velocity and `RaycastSensors` readings as
+/// inputs.
+#[derive(Debug, Component, Clone)]
+pub(crate) struct AiPilot {
+    pub(crate) brain: Brain,
+}
+
+/// Survival time plus asteroids destroyed; the genetic algorithm's score.
+#[derive(Debug, Component, Default)]
+pub(crate) struct Fitness {
+    time_alive: f32,
+    kills: u32,
+}
+
+impl Fitness {
+    fn score(&self) -> f32 {
+        self.time_alive + self.kills as f32 * 10.0
+    }
+}
+
+pub(crate) fn ai_control_system(
+    mut query: Query<(
+        &mut Velocity,
+        &RaycastSensors,
+        &SteeringControl,
+        &mut ThrustEngine,
+        &mut Weapon,
+        &AiPilot,
+    )>,
+) {
+    for (mut velocity, sensors, steering, mut thrust, mut weapon, pilot) in query.iter_mut() {
+        let mut inputs = Vec::with_capacity(N_INPUTS);
+        inputs.push(velocity.linvel.x);
+        inputs.push(velocity.linvel.y);
+        inputs.extend_from_slice(&sensors.0);
+        inputs.resize(N_INPUTS, 1.0);
+
+        let outputs = pilot.brain.forward(&inputs);
+
+        velocity.angvel = if outputs[0] > 0.5 {
+            steering.0.get()
+        } else if outputs[1] > 0.5 {
+            -steering.0.get()
+        } else {
+            0.0
+        };
+        thrust.on = outputs[2] > 0.5;
+        weapon.triggered = weapon.triggered || outputs[3] > 0.5;
+    }
+}
+
+pub(crate) fn ai_fitness_system(
+    mut hits: EventReader<crate::HitEvent<crate::Asteroid, crate::Bullet>>,
+    owners: Query<&Owner>,
+    mut pilots: Query<&mut Fitness, With<AiPilot>>,
+) {
+    for mut fitness in pilots.iter_mut() {
+        fitness.time_alive += crate::TIME_STEP;
+    }
+
+    for hit in hits.iter() {
+        let bullet = hit.entities.1;
+        if let Ok(owner) = owners.get(bullet) {
+            if let Ok(mut fitness) = pilots.get_mut(owner.0) {
+                fitness.kills += 1;
+            }
+        }
+    }
+}
+
+/// Settings for a headless training run.
+pub(crate) struct EvolutionConfig {
+    population: usize,
+    generations: usize,
+    mut_rate: f32,
+}
+
+impl EvolutionConfig {
+    /// Parses `--evolve [population] [generations] [mut_rate]` from the
+    /// process arguments; returns `None` when `--evolve` wasn't passed, so
+    /// `main` falls through to the interactive game.
+    pub(crate) fn from_args(args: impl Iterator<Item = String>) -> Option<Self> {
+        let mut args = args.skip_while(|a| a != "--evolve");
+        args.next()?;
+        Some(Self {
+            // Clamped to at least 1: a population of 0 would leave
+            // `run_evolution` ranking an empty generation.
+            population: args
+                .next()
+                .and_then(|a| a.parse().ok())
+                .unwrap_or(50)
+                .max(1),
+            generations: args.next().and_then(|a| a.parse().ok()).unwrap_or(100),
+            mut_rate: args.next().and_then(|a| a.parse().ok()).unwrap_or(0.03),
+        })
+    }
+}
+
+/// A ship, stripped of rendering and collision machinery, cheap enough to
+/// simulate in bulk. Duplicates the live physics constants rather than
+/// driving a full `App`, so a generation of hundreds of ships trains in
+/// milliseconds instead of real-time frames.
+struct SimShip {
+    position: Vec2,
+    rotation: f32,
+    velocity: Vec2,
+    angular_velocity: f32,
+    brain: Brain,
+    fitness: Fitness,
+    alive: bool,
+}
+
+struct SimAsteroid {
+    position: Vec2,
+    velocity: Vec2,
+    radius: f32,
+}
+
+const ARENA: f32 = 1280.0;
+const SPEED_LIMIT: f32 = 350.0;
+const DAMPING: f32 = 0.998;
+const THRUST_FORCE: f32 = 1.5;
+const STEERING_RATE: f32 = PI;
+const EPISODE_TICKS: usize = 120 * 30;
+
+fn cast_rays(position: Vec2, rotation: f32, asteroids: &[SimAsteroid]) -> [f32; sensors::N_RAYS] {
+    let diagonal = ARENA * 2.0_f32.sqrt();
+    let mut distances = [1.0; sensors::N_RAYS];
+    for (i, slot) in distances.iter_mut().enumerate() {
+        let angle = rotation + PI / 4.0 * i as f32;
+        let dir = Vec2::new(angle.cos(), angle.sin());
+        let mut nearest = diagonal;
+        for asteroid in asteroids {
+            let v = asteroid.position - position;
+            let cross = v.perp_dot(dir);
+            let dot = v.dot(dir);
+            if dot >= 0.0 && cross.abs() <= asteroid.radius {
+                nearest = nearest.min(dot.max(0.0));
+            }
+        }
+        *slot = (nearest / diagonal).min(1.0);
+    }
+    distances
+}
+
+fn simulate_generation(brains: &[Brain], rng: &mut SmallRng) -> Vec<f32> {
+    let mut ships: Vec<SimShip> = brains
+        .iter()
+        .map(|brain| SimShip {
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            velocity: Vec2::ZERO,
+            angular_velocity: 0.0,
+            brain: brain.clone(),
+            fitness: Fitness::default(),
+            alive: true,
+        })
+        .collect();
+
+    let mut asteroids: Vec<SimAsteroid> = (0..10)
+        .map(|_| SimAsteroid {
+            position: Vec2::new(
+                rng.gen_range(-ARENA / 2.0..ARENA / 2.0),
+                rng.gen_range(-ARENA / 2.0..ARENA / 2.0),
+            ),
+            velocity: Vec2::new(rng.gen_range(-60.0..60.0), rng.gen_range(-60.0..60.0)),
+            radius: rng.gen_range(crate::MEDIUM_ASTEROID),
+        })
+        .collect();
+
+    for _ in 0..EPISODE_TICKS {
+        if ships.iter().all(|s| !s.alive) {
+            break;
+        }
+
+        for asteroid in &mut asteroids {
+            asteroid.position += asteroid.velocity * crate::TIME_STEP;
+            wrap(&mut asteroid.position);
+        }
+
+        for ship in &mut ships {
+            if !ship.alive {
+                continue;
+            }
+
+            ship.fitness.time_alive += crate::TIME_STEP;
+
+            let rays = cast_rays(ship.position, ship.rotation, &asteroids);
+            let mut inputs = Vec::with_capacity(N_INPUTS);
+            inputs.push(ship.velocity.x);
+            inputs.push(ship.velocity.y);
+            inputs.extend_from_slice(&rays);
+            let outputs = ship.brain.forward(&inputs);
+
+            ship.angular_velocity = if outputs[0] > 0.5 {
+                STEERING_RATE
+            } else if outputs[1] > 0.5 {
+                -STEERING_RATE
+            } else {
+                0.0
+            };
+            if outputs[2] > 0.5 {
+                ship.velocity.x += ship.rotation.cos() * THRUST_FORCE;
+                ship.velocity.y += ship.rotation.sin() * THRUST_FORCE;
+            }
+            // Firing a sensor-equipped weapon in the trainer is future
+            // work; outputs[3] is reserved for it.
+
+            ship.velocity *= DAMPING;
+            ship.velocity = ship.velocity.clamp_length_max(SPEED_LIMIT);
+            ship.rotation += ship.angular_velocity * crate::TIME_STEP;
+            ship.position += ship.velocity * crate::TIME_STEP;
+            wrap(&mut ship.position);
+
+            if asteroids
+                .iter()
+                .any(|a| (a.position - ship.position).length() < a.radius + 12.0)
+            {
+                ship.alive = false;
+            }
+        }
+    }
+
+    ships.iter().map(|s| s.fitness.score()).collect()
+}
+
+fn wrap(position: &mut Vec2) {
+    let half = ARENA / 2.0;
+    if position.x < -half {
+        position.x += ARENA;
+    } else if position.x > half {
+        position.x -= ARENA;
+    }
+    if position.y < -half {
+        position.y += ARENA;
+    } else if position.y > half {
+        position.y -= ARENA;
+    }
+}
+
+/// Spawns a population, evolves it for `config.generations` generations,
+/// prints fitness stats each generation, and returns the best brain of the
+/// final generation so it can fly a live `Ship`.
+pub(crate) fn run_evolution(config: EvolutionConfig) -> Brain {
+    let mut rng = SmallRng::from_entropy();
+    let layers = vec![N_INPUTS, HIDDEN_LAYER, N_OUTPUTS];
+    let mut brains: Vec<Brain> = (0..config.population)
+        .map(|_| Brain::new(layers.clone(), Activation::ReLU, &mut rng))
+        .collect();
+    let mut best = brains[0].clone();
+
+    for generation in 0..config.generations {
+        let fitness = simulate_generation(&brains, &mut rng);
+
+        let mut ranked: Vec<usize> = (0..fitness.len()).collect();
+        ranked.sort_by(|&a, &b| fitness[b].partial_cmp(&fitness[a]).unwrap());
+        best = brains[ranked[0]].clone();
+
+        let max = fitness.iter().cloned().fold(f32::MIN, f32::max);
+        let min = fitness.iter().cloned().fold(f32::MAX, f32::min);
+        let mean = fitness.iter().sum::<f32>() / fitness.len() as f32;
+        let mut sorted = fitness.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = sorted[sorted.len() / 2];
+        println!(
+            "generation {}: max={:.1} mean={:.1} median={:.1} min={:.1}",
+            generation, max, mean, median, min
+        );
+
+        let survivors: Vec<&Brain> = ranked
+            .iter()
+            .take((brains.len() / 2).max(2))
+            .map(|&i| &brains[i])
+            .collect();
+
+        let mut next_gen = Vec::with_capacity(brains.len());
+        next_gen.extend(survivors.iter().map(|b| (*b).clone()));
+        while next_gen.len() < brains.len() {
+            let a = survivors[rng.gen_range(0..survivors.len())];
+            let b = survivors[rng.gen_range(0..survivors.len())];
+            let mut child = Brain::crossover(a, b, &mut rng);
+            child.mutate(config.mut_rate, &mut rng);
+            next_gen.push(child);
+        }
+        brains = next_gen;
+    }
+
+    best
+}
+
+/// Holds the brain `--evolve` just trained, for `spawn_ai_ship_system` to
+/// fly in the live game once training finishes.
+pub(crate) struct SpawnAiPilot(pub(crate) Brain);
+
+/// Startup system that spawns the `--evolve`-trained pilot as a real,
+/// AI-flown `Ship` instead of leaving the trained brain stuck in the
+/// headless trainer.
+pub(crate) fn spawn_ai_ship_system(mut commands: Commands, pilot: Res<SpawnAiPilot>) {
+    spawn_ai_ship(&mut commands, pilot.0.clone());
+}
+
+/// Spawns a live, AI-piloted ship.
+pub(crate) fn spawn_ai_ship(commands: &mut Commands, brain: Brain) -> Entity {
+    let entity = crate::spawn_ship(commands);
+    commands
+        .entity(entity)
+        .insert(AiPilot { brain })
+        .insert(Fitness::default());
+    entity
+}
@@ -8,16 +8,31 @@ use bevy_prototype_lyon::{
     },
     shapes::Polygon,
 };
+use bevy_rapier2d::prelude::*;
 use rand::{prelude::SmallRng, Rng, SeedableRng};
 
-const TIME_STEP: f32 = 1.0 / 120.0;
+mod ai;
+mod scripting;
+mod sensors;
 
-const BIG_ASTEROID: Range<f32> = 50.0..60.0;
-const MEDIUM_ASTEROID: Range<f32> = 30.0..40.0;
-const SMALL_ASTEROID: Range<f32> = 10.0..20.0;
+use ai::AiPilot;
+use sensors::RaycastSensors;
+
+/// Re-exported so the `ai` module can read/drive a ship's
+/// velocity without depending on `bevy_rapier2d` directly.
+pub(crate) use bevy_rapier2d::prelude::Velocity;
+
+pub(crate) const TIME_STEP: f32 = 1.0 / 120.0;
+
+pub(crate) const BIG_ASTEROID: Range<f32> = 50.0..60.0;
+pub(crate) const MEDIUM_ASTEROID: Range<f32> = 30.0..40.0;
+pub(crate) const SMALL_ASTEROID: Range<f32> = 10.0..20.0;
 
 fn main() {
-    App::new()
+    let evolved_pilot = ai::EvolutionConfig::from_args(std::env::args()).map(ai::run_evolution);
+
+    let mut app = App::new();
+    app
         .insert_resource(WindowDescriptor {
             title: "Bevyroids".to_string(),
             present_mode: PresentMode::Fifo,
@@ -26,46 +41,105 @@ fn main() {
         .insert_resource(Msaa { samples: 4 })
         .add_plugins(DefaultPlugins)
         .add_plugin(ShapePlugin)
+        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+        .insert_resource(RapierConfiguration {
+            gravity: Vec2::ZERO,
+            timestep_mode: TimestepMode::Fixed {
+                dt: TIME_STEP,
+                substeps: 1,
+            },
+            ..default()
+        })
         .insert_resource(Random(SmallRng::from_entropy()))
         .add_event::<AsteroidSpawnEvent>()
         .add_event::<HitEvent<Asteroid, Bullet>>()
         .add_event::<HitEvent<Asteroid, Ship>>()
+        .add_event::<HitEvent<Hostile, Bullet>>()
+        .add_event::<HitEvent<Hostile, Ship>>()
+        .add_startup_system(scripting::load_scripts_system)
         .add_startup_system(setup_system)
+        .add_system(sensors::raycast_sensor_system.before("input"))
         .add_system_set(
             SystemSet::new()
                 .label("input")
                 .with_system(steering_control_system)
                 .with_system(thrust_control_system)
-                .with_system(weapon_control_system),
+                .with_system(weapon_control_system)
+                .with_system(ai::ai_control_system)
+                .with_system(scripting::scripted_behavior_system),
         )
         .add_system(weapon_system.after("input").before("physics"))
         .add_system(thrust_system.after("input").before("physics"))
         .add_system(asteroid_spawn_system.with_run_criteria(FixedTimestep::step(0.5)))
         .add_system(asteroid_generation_system)
+        .add_system(scripting::scripted_wave_system.with_run_criteria(FixedTimestep::step(5.0)))
         .add_system_set(
             SystemSet::new()
                 .with_run_criteria(FixedTimestep::step(TIME_STEP.into()))
                 .label("physics")
                 .after("input")
-                .with_system(damping_system.before(movement_system))
-                .with_system(speed_limit_system.before(movement_system))
-                .with_system(movement_system),
+                .with_system(speed_limit_system)
+                .with_system(travel_budget_system),
         )
         .add_system_set(
             SystemSet::new()
                 .label("wrap")
                 .after("physics")
-                .with_system(boundary_remove_system)
-                .with_system(boundary_wrap_system),
+                .with_system(spatial_sync_system)
+                .with_system(boundary_remove_system.after(spatial_sync_system))
+                .with_system(boundary_wrap_system.after(spatial_sync_system)),
         )
         .add_system(collision_system)
         .add_system(asteroid_hit_system)
-        .add_system(drawing_system.after("wrap"))
-        .run();
+        .add_system(ship_hit_system)
+        .add_system(hostile_bullet_hit_system)
+        .add_system(hostile_ship_hit_system)
+        .add_system(asteroid_retire_system)
+        .add_system(ai::ai_fitness_system);
+
+    if let Some(brain) = evolved_pilot {
+        app.insert_resource(ai::SpawnAiPilot(brain))
+            .add_startup_system(ai::spawn_ai_ship_system);
+    } else {
+        app.add_startup_system(spawn_player_ship_system);
+    }
+
+    app.run();
+}
+
+/// Spawns a ship's rendering shape and `ShipBundle` at the origin, without
+/// attaching a pilot; callers add either keyboard control (the default) or
+/// `AiPilot`/`Fitness` for an AI-flown ship.
+pub(crate) fn spawn_ship(commands: &mut Commands) -> Entity {
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &{
+                let mut path_builder = PathBuilder::new();
+                path_builder.move_to(Vec2::ZERO);
+                path_builder.line_to(Vec2::new(-8.0, -8.0));
+                path_builder.line_to(Vec2::new(0.0, 12.0));
+                path_builder.line_to(Vec2::new(8.0, -8.0));
+                path_builder.line_to(Vec2::ZERO);
+                let mut line = path_builder.build();
+                line.0 = line.0.transformed(&Rotation::new(Angle::degrees(-90.0)));
+                line
+            },
+            DrawMode::Stroke(StrokeMode::new(Color::BLACK, 1.0)),
+            Transform::default(),
+        ))
+        .insert_bundle(ShipBundle::default())
+        .id()
+}
+
+/// Spawns the keyboard-controlled ship; only added as a startup system when
+/// `main` didn't just train (and is about to spawn) an `AiPilot` instead, so
+/// the two never end up stacked on top of each other at the origin.
+fn spawn_player_ship_system(mut commands: Commands) {
+    spawn_ship(&mut commands);
 }
 
 #[derive(Debug, Deref, DerefMut)]
-struct Random(SmallRng);
+pub(crate) struct Random(SmallRng);
 
 impl FromWorld for Random {
     fn from_world(world: &mut World) -> Self {
@@ -77,35 +151,19 @@ impl FromWorld for Random {
 }
 
 #[derive(Debug, Component, Default, Clone)]
-struct Spatial {
-    position: Vec2,
-    rotation: f32,
-    radius: f32,
-}
-
-impl Spatial {
-    fn intersects(&self, other: &Spatial) -> bool {
-        let distance = (self.position - other.position).length();
-        distance < self.radius + other.radius
-    }
+pub(crate) struct Spatial {
+    pub(crate) position: Vec2,
+    pub(crate) rotation: f32,
+    pub(crate) radius: f32,
 }
 
-#[derive(Debug, Component, Default)]
-struct Velocity(Vec2);
-
-#[derive(Debug, Component, Default)]
-struct AngularVelocity(f32);
-
 #[derive(Debug, Component, Default)]
 struct SpeedLimit(f32);
 
 #[derive(Debug, Component, Default)]
-struct Damping(f32);
-
-#[derive(Debug, Component, Default)]
-struct ThrustEngine {
+pub(crate) struct ThrustEngine {
     force: f32,
-    on: bool,
+    pub(crate) on: bool,
 }
 
 impl ThrustEngine {
@@ -118,12 +176,12 @@ impl ThrustEngine {
 }
 
 #[derive(Debug, Component, Default)]
-struct SteeringControl(Angle);
+pub(crate) struct SteeringControl(pub(crate) Angle);
 
 #[derive(Debug, Component, Default)]
-struct Weapon {
+pub(crate) struct Weapon {
     cooldown: Timer,
-    triggered: bool,
+    pub(crate) triggered: bool,
 }
 
 impl Weapon {
@@ -136,26 +194,45 @@ impl Weapon {
 }
 
 #[derive(Debug, Component, Default)]
-struct BoundaryWrap;
+pub(crate) struct BoundaryWrap;
 
 #[derive(Debug, Component, Default)]
 struct BoundaryRemoval;
 
+/// Accumulated path length travelled, in lieu of tracking absolute position
+/// for retirement purposes.
+#[derive(Debug, Component, Default)]
+struct TravelBudget(f32);
+
 #[derive(Debug, Component, Default)]
-struct Ship;
+pub(crate) struct Ship;
 
 #[derive(Debug, Component, Default)]
-struct Bullet;
+pub(crate) struct Bullet;
 
+/// Marks the ship that fired a bullet, so kills can be credited back to it.
+#[derive(Debug, Component)]
+pub(crate) struct Owner(pub(crate) Entity);
+
+#[derive(Debug, Component, Default)]
+pub(crate) struct Asteroid;
+
+/// A scripted enemy that can be shot and shoots back, distinct from the
+/// fragment-splitting `Asteroid`.
 #[derive(Debug, Component, Default)]
-struct Asteroid;
+pub(crate) struct Hostile;
 
-#[derive(Debug, Deref)]
-struct AsteroidSpawnEvent(Spatial);
+#[derive(Debug)]
+pub(crate) struct AsteroidSpawnEvent {
+    pub(crate) spatial: Spatial,
+    /// The splitting parent's velocity, if any; blended into the fragment's
+    /// own velocity so splits carry some of its momentum.
+    pub(crate) inherited_velocity: Option<Vec2>,
+}
 
 #[derive(Debug)]
-struct HitEvent<A, B> {
-    entities: (Entity, Entity),
+pub(crate) struct HitEvent<A, B> {
+    pub(crate) entities: (Entity, Entity),
     _phantom: PhantomData<(A, B)>,
 }
 
@@ -166,69 +243,106 @@ fn hit_event<A, B>(e1: Entity, e2: Entity) -> HitEvent<A, B> {
     }
 }
 
-fn setup_system(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+/// The gameplay components every ship needs, independent of whether a human
+/// or an `AiPilot` is driving it.
+#[derive(Bundle)]
+pub(crate) struct ShipBundle {
+    pub(crate) spatial: Spatial,
+    ship: Ship,
+    rigid_body: RigidBody,
+    collider: Collider,
+    velocity: Velocity,
+    damping: Damping,
+    gravity_scale: GravityScale,
+    active_events: ActiveEvents,
+    speed_limit: SpeedLimit,
+    thrust_engine: ThrustEngine,
+    steering: SteeringControl,
+    weapon: Weapon,
+    boundary_wrap: BoundaryWrap,
+    sensors: RaycastSensors,
+}
 
-    commands
-        .spawn_bundle(GeometryBuilder::build_as(
-            &{
-                let mut path_builder = PathBuilder::new();
-                path_builder.move_to(Vec2::ZERO);
-                path_builder.line_to(Vec2::new(-8.0, -8.0));
-                path_builder.line_to(Vec2::new(0.0, 12.0));
-                path_builder.line_to(Vec2::new(8.0, -8.0));
-                path_builder.line_to(Vec2::ZERO);
-                let mut line = path_builder.build();
-                line.0 = line.0.transformed(&Rotation::new(Angle::degrees(-90.0)));
-                line
+impl Default for ShipBundle {
+    fn default() -> Self {
+        let radius = 12.0;
+        Self {
+            spatial: Spatial {
+                position: Vec2::ZERO,
+                rotation: 0.0,
+                radius,
             },
-            DrawMode::Stroke(StrokeMode::new(Color::BLACK, 1.0)),
-            Transform::default(),
-        ))
-        .insert(Spatial {
-            position: Vec2::ZERO,
-            rotation: 0.0,
-            radius: 12.0,
-        })
-        .insert(Ship)
-        .insert(Velocity::default())
-        .insert(SpeedLimit(350.0))
-        .insert(Damping(0.998))
-        .insert(ThrustEngine::new(1.5))
-        .insert(AngularVelocity::default())
-        .insert(SteeringControl(Angle::degrees(180.0)))
-        .insert(Weapon::new(Duration::from_millis(100)))
-        .insert(BoundaryWrap);
-}
-
-fn movement_system(mut query: Query<(&mut Spatial, Option<&Velocity>, Option<&AngularVelocity>)>) {
-    for (mut spatial, velocity, angular_velocity) in query.iter_mut() {
-        if let Some(velocity) = velocity {
-            spatial.position += velocity.0 * TIME_STEP;
-        }
-        if let Some(angular_velocity) = angular_velocity {
-            spatial.rotation += angular_velocity.0 * TIME_STEP;
+            ship: Ship,
+            rigid_body: RigidBody::Dynamic,
+            collider: Collider::ball(radius),
+            velocity: Velocity::zero(),
+            // Approximates the old per-tick 0.998 multiplicative decay as a
+            // continuous damping coefficient.
+            damping: Damping {
+                linear_damping: 0.24,
+                angular_damping: 0.0,
+            },
+            gravity_scale: GravityScale(0.0),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            speed_limit: SpeedLimit(350.0),
+            thrust_engine: ThrustEngine::new(1.5),
+            steering: SteeringControl(Angle::degrees(180.0)),
+            weapon: Weapon::new(Duration::from_millis(100)),
+            boundary_wrap: BoundaryWrap,
+            sensors: RaycastSensors::default(),
         }
     }
 }
 
+fn setup_system(
+    window: Res<WindowDescriptor>,
+    mut rng: Local<Random>,
+    mut commands: Commands,
+    mut asteroid_spawn: EventWriter<AsteroidSpawnEvent>,
+) {
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    let w = window.width / 2.0;
+    let h = window.height / 2.0;
+    for _ in 0..2 {
+        asteroid_spawn.send(AsteroidSpawnEvent {
+            spatial: Spatial {
+                position: Vec2::new(rng.gen_range(-w..w), rng.gen_range(-h..h)),
+                radius: rng.gen_range(BIG_ASTEROID),
+                ..Default::default()
+            },
+            inherited_velocity: None,
+        });
+    }
+}
+
+/// Copies rapier's simulated `Transform` into `Spatial`, which every other
+/// gameplay system (sensors, thrust, weapons, the area budget) treats as the
+/// read-only view of where things are.
+fn spatial_sync_system(mut query: Query<(&mut Spatial, &Transform)>) {
+    for (mut spatial, transform) in query.iter_mut() {
+        spatial.position = transform.translation.truncate();
+        spatial.rotation = transform.rotation.to_euler(EulerRot::ZYX).0;
+    }
+}
+
 fn speed_limit_system(mut query: Query<(&mut Velocity, &SpeedLimit)>) {
     for (mut velocity, speed_limit) in query.iter_mut() {
-        velocity.0 = velocity.0.clamp_length_max(speed_limit.0);
+        velocity.linvel = velocity.linvel.clamp_length_max(speed_limit.0);
     }
 }
 
-fn damping_system(mut query: Query<(&mut Velocity, &Damping)>) {
-    for (mut velocity, damping) in query.iter_mut() {
-        velocity.0 *= damping.0;
+fn travel_budget_system(mut query: Query<(&mut TravelBudget, &Velocity)>) {
+    for (mut budget, velocity) in query.iter_mut() {
+        budget.0 += velocity.linvel.length() * TIME_STEP;
     }
 }
 
 fn thrust_system(mut query: Query<(&mut Velocity, &ThrustEngine, &Spatial)>) {
     for (mut velocity, thrust, spatial) in query.iter_mut() {
         if thrust.on {
-            velocity.0.x += spatial.rotation.cos() * thrust.force;
-            velocity.0.y += spatial.rotation.sin() * thrust.force;
+            velocity.linvel.x += spatial.rotation.cos() * thrust.force;
+            velocity.linvel.y += spatial.rotation.sin() * thrust.force;
         }
     }
 }
@@ -236,9 +350,9 @@ fn thrust_system(mut query: Query<(&mut Velocity, &ThrustEngine, &Spatial)>) {
 fn weapon_system(
     time: Res<Time>,
     mut commands: Commands,
-    mut query: Query<(&Spatial, &mut Weapon)>,
+    mut query: Query<(Entity, &Spatial, &mut Weapon)>,
 ) {
-    for (spatial, mut weapon) in query.iter_mut() {
+    for (ship_entity, spatial, mut weapon) in query.iter_mut() {
         weapon.cooldown.tick(time.delta());
 
         if weapon.cooldown.finished() && weapon.triggered {
@@ -262,75 +376,137 @@ fn weapon_system(
                     )),
                 ))
                 .insert(Bullet)
+                .insert(Owner(ship_entity))
                 .insert(Spatial {
                     position: bullet_pos,
                     rotation: 0.0,
                     radius: 2.0,
                 })
-                .insert(Velocity(bullet_vel))
+                .insert(RigidBody::Dynamic)
+                .insert(Collider::ball(2.0))
+                .insert(Sensor)
+                .insert(GravityScale(0.0))
+                .insert(ActiveEvents::COLLISION_EVENTS)
+                .insert(Velocity {
+                    linvel: bullet_vel,
+                    angvel: 0.0,
+                })
                 .insert(BoundaryRemoval);
         }
     }
 }
 
+/// Turns rapier's narrow-phase contact/intersection events into our own
+/// `HitEvent`s, so downstream systems stay oblivious to the physics backend.
 fn collision_system(
-    mut asteroid_hits: EventWriter<HitEvent<Asteroid, Bullet>>,
-    mut commands: Commands,
-    ships: Query<(Entity, &Spatial), With<Ship>>,
-    asteroids: Query<(Entity, &Spatial), With<Asteroid>>,
-    bullets: Query<(Entity, &Spatial), With<Bullet>>,
+    mut collisions: EventReader<CollisionEvent>,
+    mut asteroid_bullet_hits: EventWriter<HitEvent<Asteroid, Bullet>>,
+    mut asteroid_ship_hits: EventWriter<HitEvent<Asteroid, Ship>>,
+    mut hostile_bullet_hits: EventWriter<HitEvent<Hostile, Bullet>>,
+    mut hostile_ship_hits: EventWriter<HitEvent<Hostile, Ship>>,
+    asteroids: Query<Entity, With<Asteroid>>,
+    bullets: Query<Entity, With<Bullet>>,
+    ships: Query<Entity, With<Ship>>,
+    hostiles: Query<Entity, With<Hostile>>,
 ) {
-    for (bullet_entity, bullet) in bullets.iter() {
-        for (asteroid_entity, asteroid) in asteroids.iter() {
-            if bullet.intersects(asteroid) {
-                asteroid_hits.send(hit_event::<Asteroid, Bullet>(
-                    asteroid_entity,
-                    bullet_entity,
-                ))
+    for event in collisions.iter() {
+        let (e1, e2) = match event {
+            CollisionEvent::Started(e1, e2, _) => (*e1, *e2),
+            CollisionEvent::Stopped(..) => continue,
+        };
+
+        for (a, b) in [(e1, e2), (e2, e1)] {
+            if let (Ok(asteroid), Ok(bullet)) = (asteroids.get(a), bullets.get(b)) {
+                asteroid_bullet_hits.send(hit_event::<Asteroid, Bullet>(asteroid, bullet));
+            } else if let (Ok(asteroid), Ok(ship)) = (asteroids.get(a), ships.get(b)) {
+                asteroid_ship_hits.send(hit_event::<Asteroid, Ship>(asteroid, ship));
+            } else if let (Ok(hostile), Ok(bullet)) = (hostiles.get(a), bullets.get(b)) {
+                hostile_bullet_hits.send(hit_event::<Hostile, Bullet>(hostile, bullet));
+            } else if let (Ok(hostile), Ok(ship)) = (hostiles.get(a), ships.get(b)) {
+                hostile_ship_hits.send(hit_event::<Hostile, Ship>(hostile, ship));
             }
         }
     }
+}
 
-    for (_ship_entity, ship) in ships.iter() {
-        for (asteroid_entity, asteroid) in asteroids.iter() {
-            if ship.intersects(asteroid) {
-                println!("Asteroid hit ship!");
-                commands.entity(asteroid_entity).despawn();
-            }
-        }
+fn ship_hit_system(mut ship_hits: EventReader<HitEvent<Asteroid, Ship>>, mut commands: Commands) {
+    for hit in ship_hits.iter() {
+        println!("Asteroid hit ship!");
+        commands.entity(hit.entities.0).despawn();
+    }
+}
+
+fn hostile_bullet_hit_system(
+    mut hits: EventReader<HitEvent<Hostile, Bullet>>,
+    mut commands: Commands,
+) {
+    for hit in hits.iter() {
+        commands.entity(hit.entities.0).despawn();
+        commands.entity(hit.entities.1).despawn();
+    }
+}
+
+fn hostile_ship_hit_system(mut hits: EventReader<HitEvent<Hostile, Ship>>, mut commands: Commands) {
+    for hit in hits.iter() {
+        println!("Hostile hit ship!");
+        commands.entity(hit.entities.0).despawn();
+    }
+}
+
+/// Total live asteroid "area" the field is allowed to hold before the
+/// spawner stops emitting new ones.
+const ASTEROID_AREA_BUDGET: u32 = 12;
+
+fn asteroid_area(radius: f32) -> u32 {
+    if BIG_ASTEROID.contains(&radius) {
+        4
+    } else if MEDIUM_ASTEROID.contains(&radius) {
+        2
+    } else {
+        1
     }
 }
 
 fn asteroid_spawn_system(
     window: Res<WindowDescriptor>,
     mut rng: Local<Random>,
-    mut asteroids: EventWriter<AsteroidSpawnEvent>,
+    asteroids: Query<&Spatial, With<Asteroid>>,
+    mut asteroid_spawn: EventWriter<AsteroidSpawnEvent>,
 ) {
-    if rng.gen_bool(1.0 / 3.0) {
-        let w = window.width / 2.0;
-        let h = window.height / 2.0;
-
-        let x = rng.gen_range(-w..w);
-        let y = rng.gen_range(-h..h);
-        let radius = match rng.gen_range(1..=3) {
-            3 => rng.gen_range(BIG_ASTEROID),
-            2 => rng.gen_range(MEDIUM_ASTEROID),
-            _ => rng.gen_range(SMALL_ASTEROID),
-        };
-        let c = radius * 2.0;
+    let total_area: u32 = asteroids
+        .iter()
+        .map(|spatial| asteroid_area(spatial.radius))
+        .sum();
+    if total_area >= ASTEROID_AREA_BUDGET {
+        return;
+    }
 
-        let position = if rng.gen_bool(1.0 / 2.0) {
-            Vec2::new(x, if y > 0.0 { h + c } else { -h - c })
-        } else {
-            Vec2::new(if x > 0.0 { w + c } else { -w - c }, y)
-        };
+    let w = window.width / 2.0;
+    let h = window.height / 2.0;
 
-        asteroids.send(AsteroidSpawnEvent(Spatial {
+    let x = rng.gen_range(-w..w);
+    let y = rng.gen_range(-h..h);
+    let radius = match rng.gen_range(1..=3) {
+        3 => rng.gen_range(BIG_ASTEROID),
+        2 => rng.gen_range(MEDIUM_ASTEROID),
+        _ => rng.gen_range(SMALL_ASTEROID),
+    };
+    let c = radius * 2.0;
+
+    let position = if rng.gen_bool(1.0 / 2.0) {
+        Vec2::new(x, if y > 0.0 { h + c } else { -h - c })
+    } else {
+        Vec2::new(if x > 0.0 { w + c } else { -w - c }, y)
+    };
+
+    asteroid_spawn.send(AsteroidSpawnEvent {
+        spatial: Spatial {
             position,
             radius,
             ..Default::default()
-        }));
-    }
+        },
+        inherited_velocity: None,
+    });
 }
 
 fn asteroid_generation_system(
@@ -343,70 +519,88 @@ fn asteroid_generation_system(
     let h = window.height / 2.0;
 
     for asteroid in asteroids.iter() {
-        let position = asteroid.position;
+        let position = asteroid.spatial.position;
 
         let velocity = Vec2::new(rng.gen_range(-w..w), rng.gen_range(-h..h));
-        let scale = if BIG_ASTEROID.contains(&asteroid.radius) {
+        let scale = if BIG_ASTEROID.contains(&asteroid.spatial.radius) {
             rng.gen_range(30.0..60.0)
-        } else if MEDIUM_ASTEROID.contains(&asteroid.radius) {
+        } else if MEDIUM_ASTEROID.contains(&asteroid.spatial.radius) {
             rng.gen_range(60.0..80.0)
         } else {
             rng.gen_range(80.0..100.0)
         };
         let velocity = (velocity - position).normalize_or_zero() * scale;
+        // Splitting asteroids hand down half their own momentum instead of
+        // fragments scattering on purely random headings.
+        let velocity = velocity + asteroid.inherited_velocity.unwrap_or(Vec2::ZERO) * 0.5;
 
-        let shape = {
+        let points = {
             let sides = rng.gen_range(6..12);
             let mut points = Vec::with_capacity(sides);
             let n = sides as f32;
             let internal = (n - 2.0) * PI / n;
             let offset = -internal / 2.0;
             let step = 2.0 * PI / n;
-            let r = asteroid.radius;
+            let r = asteroid.spatial.radius;
             for i in 0..sides {
                 let cur_angle = (i as f32).mul_add(step, offset);
                 let x = r * rng.gen_range(0.5..1.2) * cur_angle.cos();
                 let y = r * rng.gen_range(0.5..1.2) * cur_angle.sin();
                 points.push(Vec2::new(x, y));
             }
-            Polygon {
-                points,
-                closed: true,
-            }
+            points
         };
+        // Matches the collider to the rendered silhouette instead of a
+        // bounding circle; degenerate point sets fall back to one.
+        let collider = Collider::convex_hull(&points)
+            .unwrap_or_else(|| Collider::ball(asteroid.spatial.radius));
 
         commands
             .spawn_bundle(GeometryBuilder::build_as(
-                &shape,
+                &Polygon {
+                    points,
+                    closed: true,
+                },
                 DrawMode::Stroke(StrokeMode::new(Color::BLACK, 1.0)),
                 Transform::default().with_translation(Vec3::new(position.x, position.y, 0.0)),
             ))
             .insert(Asteroid)
-            .insert(asteroid.0.clone())
-            .insert(Velocity(velocity))
-            .insert(AngularVelocity(rng.gen_range(-3.0..3.0)))
-            .insert(BoundaryRemoval);
+            .insert(asteroid.spatial.clone())
+            .insert(RigidBody::Dynamic)
+            .insert(collider)
+            .insert(Velocity {
+                linvel: velocity,
+                angvel: rng.gen_range(-3.0..3.0),
+            })
+            .insert(GravityScale(0.0))
+            .insert(ActiveEvents::COLLISION_EVENTS)
+            .insert(BoundaryWrap)
+            .insert(TravelBudget::default());
     }
 }
 
 fn boundary_wrap_system(
     window: Res<WindowDescriptor>,
-    mut query: Query<&mut Spatial, With<BoundaryWrap>>,
+    mut query: Query<(&mut Transform, &mut Spatial), With<BoundaryWrap>>,
 ) {
-    for mut spatial in query.iter_mut() {
+    for (mut transform, mut spatial) in query.iter_mut() {
         let half_width = window.width / 2.0;
         if spatial.position.x + spatial.radius * 2.0 < -half_width {
-            spatial.position.x = half_width + spatial.radius * 2.0;
+            transform.translation.x = half_width + spatial.radius * 2.0;
         } else if spatial.position.x - spatial.radius * 2.0 > half_width {
-            spatial.position.x = -half_width - spatial.radius * 2.0;
+            transform.translation.x = -half_width - spatial.radius * 2.0;
         }
 
         let half_height = window.height / 2.0;
         if spatial.position.y + spatial.radius * 2.0 < -half_height {
-            spatial.position.y = half_height + spatial.radius * 2.0;
+            transform.translation.y = half_height + spatial.radius * 2.0;
         } else if spatial.position.y - spatial.radius * 2.0 > half_height {
-            spatial.position.y = -half_height - spatial.radius * 2.0;
+            transform.translation.y = -half_height - spatial.radius * 2.0;
         }
+
+        // Keep the read-model in lock-step with the teleport above, instead
+        // of waiting a frame for `spatial_sync_system` to notice.
+        spatial.position = transform.translation.truncate();
     }
 }
 
@@ -428,69 +622,88 @@ fn boundary_remove_system(
     }
 }
 
+fn asteroid_retire_system(
+    window: Res<WindowDescriptor>,
+    mut commands: Commands,
+    query: Query<(Entity, &TravelBudget), With<Asteroid>>,
+) {
+    let diagonal = (window.width.powi(2) + window.height.powi(2)).sqrt();
+    for (entity, budget) in query.iter() {
+        if budget.0 >= diagonal {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
 fn steering_control_system(
     keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&mut AngularVelocity, &SteeringControl)>,
+    mut query: Query<(&mut Velocity, &SteeringControl), Without<AiPilot>>,
 ) {
-    for (mut angular_velocity, steering) in query.iter_mut() {
+    for (mut velocity, steering) in query.iter_mut() {
         if keyboard_input.pressed(KeyCode::Left) {
-            angular_velocity.0 = steering.0.get();
+            velocity.angvel = steering.0.get();
         } else if keyboard_input.pressed(KeyCode::Right) {
-            angular_velocity.0 = -steering.0.get();
+            velocity.angvel = -steering.0.get();
         } else {
-            angular_velocity.0 = 0.0;
+            velocity.angvel = 0.0;
         }
     }
 }
 
-fn thrust_control_system(keyboard_input: Res<Input<KeyCode>>, mut query: Query<&mut ThrustEngine>) {
+fn thrust_control_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut ThrustEngine, Without<AiPilot>>,
+) {
     for mut thrust_engine in query.iter_mut() {
         thrust_engine.on = keyboard_input.pressed(KeyCode::Up)
     }
 }
 
-fn weapon_control_system(keyboard_input: Res<Input<KeyCode>>, mut query: Query<&mut Weapon>) {
+fn weapon_control_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut Weapon, Without<AiPilot>>,
+) {
     for mut weapon in query.iter_mut() {
         weapon.triggered = weapon.triggered || keyboard_input.just_pressed(KeyCode::Space);
     }
 }
 
-fn drawing_system(mut query: Query<(&mut Transform, &Spatial)>) {
-    for (mut transform, spatial) in query.iter_mut() {
-        transform.translation.x = spatial.position.x;
-        transform.translation.y = spatial.position.y;
-        transform.rotation = Quat::from_rotation_z(spatial.rotation);
-    }
-}
-
 fn asteroid_hit_system(
     mut rng: Local<Random>,
     mut asteroid_hits: EventReader<HitEvent<Asteroid, Bullet>>,
     mut asteroid_spawn: EventWriter<AsteroidSpawnEvent>,
     mut commands: Commands,
-    query: Query<&Spatial, With<Asteroid>>,
+    query: Query<(&Spatial, &Velocity), With<Asteroid>>,
 ) {
     for hit in asteroid_hits.iter() {
         let asteroid = hit.entities.0;
         let bullet = hit.entities.1;
 
-        if let Ok(spatial) = query.get(asteroid) {
+        if let Ok((spatial, velocity)) = query.get(asteroid) {
+            let inherited_velocity = Some(velocity.linvel);
             if BIG_ASTEROID.contains(&spatial.radius) {
                 let spatial = Spatial {
                     radius: rng.gen_range(MEDIUM_ASTEROID),
                     ..spatial.clone()
                 };
 
-                asteroid_spawn.send(AsteroidSpawnEvent(spatial.clone()));
-                asteroid_spawn.send(AsteroidSpawnEvent(spatial.clone()));
-                asteroid_spawn.send(AsteroidSpawnEvent(spatial.clone()));
+                for _ in 0..3 {
+                    asteroid_spawn.send(AsteroidSpawnEvent {
+                        spatial: spatial.clone(),
+                        inherited_velocity,
+                    });
+                }
             } else if MEDIUM_ASTEROID.contains(&spatial.radius) {
                 let spatial = Spatial {
                     radius: rng.gen_range(SMALL_ASTEROID),
                     ..spatial.clone()
                 };
-                asteroid_spawn.send(AsteroidSpawnEvent(spatial.clone()));
-                asteroid_spawn.send(AsteroidSpawnEvent(spatial.clone()));
+                for _ in 0..2 {
+                    asteroid_spawn.send(AsteroidSpawnEvent {
+                        spatial: spatial.clone(),
+                        inherited_velocity,
+                    });
+                }
             }
         }
 
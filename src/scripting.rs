@@ -0,0 +1,278 @@
+//! Embeds a Rhai runtime so asteroid/enemy movement patterns and spawn
+//! "directives" can be tuned by editing a script instead of recompiling.
+//!
+//! Each `Scripted` entity names a compiled program by [`ScriptId`]; every
+//! fixed tick, [`scripted_behavior_system`] hands that program a small
+//! [`ScriptApi`] value describing the entity's state and the surrounding
+//! world, calls its `update` function, and applies whatever the script asked
+//! for (steering toward a point, firing, spawning asteroids) back onto the
+//! real ECS components.
+
+use std::{fs, time::Duration};
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use bevy_rapier2d::prelude::{ActiveEvents, Collider, GravityScale, RigidBody};
+use rand::Rng;
+use rhai::{Engine, Scope, AST};
+
+use crate::{AsteroidSpawnEvent, BoundaryWrap, Hostile, Random, Ship, Spatial, Velocity, Weapon};
+
+/// How often a new scripted hostile enters the field.
+const WAVE_INTERVAL: f64 = 5.0;
+
+/// Where compiled scripts are loaded from at startup.
+const SCRIPT_DIR: &str = "assets/scripts";
+
+/// Index into [`ScriptAssets`]' compiled program list.
+#[derive(Debug, Component, Clone, Copy)]
+pub(crate) struct Scripted {
+    pub(crate) handle: ScriptId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ScriptId(usize);
+
+/// The Rhai engine and every script compiled from [`SCRIPT_DIR`], indexed by
+/// [`ScriptId`].
+pub(crate) struct ScriptAssets {
+    engine: Engine,
+    programs: Vec<AST>,
+}
+
+impl FromWorld for ScriptAssets {
+    fn from_world(_world: &mut World) -> Self {
+        let mut engine = Engine::new();
+        engine
+            .register_type_with_name::<ScriptApi>("ScriptApi")
+            .register_get("position_x", ScriptApi::position_x)
+            .register_get("position_y", ScriptApi::position_y)
+            .register_get("rotation", ScriptApi::rotation)
+            .register_get("velocity_x", ScriptApi::velocity_x)
+            .register_get("velocity_y", ScriptApi::velocity_y)
+            .register_get("angvel", ScriptApi::angvel)
+            .register_get("window_w", ScriptApi::window_w)
+            .register_get("window_h", ScriptApi::window_h)
+            .register_fn("has_nearest_ship", ScriptApi::has_nearest_ship)
+            .register_fn("nearest_ship_x", ScriptApi::nearest_ship_x)
+            .register_fn("nearest_ship_y", ScriptApi::nearest_ship_y)
+            .register_fn("thrust_toward", ScriptApi::thrust_toward)
+            .register_fn("fire", ScriptApi::fire)
+            .register_fn("spawn_asteroid", ScriptApi::spawn_asteroid);
+
+        let mut programs = Vec::new();
+        if let Ok(entries) = fs::read_dir(SCRIPT_DIR) {
+            let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+            paths.sort_by_key(|entry| entry.file_name());
+            for entry in paths {
+                let path = entry.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("rhai") {
+                    continue;
+                }
+                match engine.compile_file(path.clone()) {
+                    Ok(ast) => programs.push(ast),
+                    Err(err) => warn!("failed to compile script {:?}: {}", path, err),
+                }
+            }
+        }
+
+        Self { engine, programs }
+    }
+}
+
+impl ScriptAssets {
+    /// Runs `id`'s `update` function against `api`, returning the script's
+    /// (possibly mutated) copy of it.
+    fn update(&self, id: ScriptId, api: ScriptApi) -> Result<ScriptApi, Box<rhai::EvalAltResult>> {
+        let ast = &self.programs[id.0];
+        let mut scope = Scope::new();
+        self.engine.call_fn(&mut scope, ast, "update", (api,))
+    }
+}
+
+/// The read/write view a script gets of one entity and its surroundings.
+/// Values are passed by copy: a script mutates its local binding via the
+/// registered methods below and returns it, so the host applies whatever
+/// changed once the call returns.
+#[derive(Debug, Clone, Default)]
+struct ScriptApi {
+    position: Vec2,
+    rotation: f64,
+    velocity: Vec2,
+    angvel: f64,
+    nearest_ship: Option<Vec2>,
+    window: Vec2,
+    thrust_target: Option<Vec2>,
+    fire: bool,
+    spawns: Vec<(Vec2, f32)>,
+}
+
+impl ScriptApi {
+    fn position_x(&mut self) -> f64 {
+        self.position.x as f64
+    }
+
+    fn position_y(&mut self) -> f64 {
+        self.position.y as f64
+    }
+
+    fn rotation(&mut self) -> f64 {
+        self.rotation
+    }
+
+    fn velocity_x(&mut self) -> f64 {
+        self.velocity.x as f64
+    }
+
+    fn velocity_y(&mut self) -> f64 {
+        self.velocity.y as f64
+    }
+
+    fn angvel(&mut self) -> f64 {
+        self.angvel
+    }
+
+    fn window_w(&mut self) -> f64 {
+        self.window.x as f64
+    }
+
+    fn window_h(&mut self) -> f64 {
+        self.window.y as f64
+    }
+
+    fn has_nearest_ship(&mut self) -> bool {
+        self.nearest_ship.is_some()
+    }
+
+    fn nearest_ship_x(&mut self) -> f64 {
+        self.nearest_ship.map_or(0.0, |p| p.x as f64)
+    }
+
+    fn nearest_ship_y(&mut self) -> f64 {
+        self.nearest_ship.map_or(0.0, |p| p.y as f64)
+    }
+
+    fn thrust_toward(&mut self, x: f64, y: f64) {
+        self.thrust_target = Some(Vec2::new(x as f32, y as f32));
+    }
+
+    fn fire(&mut self) {
+        self.fire = true;
+    }
+
+    fn spawn_asteroid(&mut self, x: f64, y: f64, radius: f64) {
+        self.spawns.push((Vec2::new(x as f32, y as f32), radius as f32));
+    }
+}
+
+pub(crate) fn load_scripts_system(world: &mut World) {
+    let assets = ScriptAssets::from_world(world);
+    world.insert_resource(assets);
+}
+
+/// Spawns a scripted hostile running a uniformly-random loaded program, so
+/// over enough waves every `assets/scripts/*.rhai` file gets flown. This is
+/// the one spawn path that attaches `Scripted`; without it no script ever
+/// runs.
+pub(crate) fn scripted_wave_system(
+    window: Res<WindowDescriptor>,
+    mut rng: Local<Random>,
+    assets: Res<ScriptAssets>,
+    mut commands: Commands,
+) {
+    if assets.programs.is_empty() {
+        return;
+    }
+
+    let handle = ScriptId(rng.gen_range(0..assets.programs.len()));
+    let w = window.width / 2.0;
+    let h = window.height / 2.0;
+    let position = Vec2::new(rng.gen_range(-w..w), rng.gen_range(-h..h));
+    let radius = rng.gen_range(crate::SMALL_ASTEROID);
+
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Circle {
+                radius,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Stroke(StrokeMode::new(Color::RED, 1.0)),
+            Transform::default().with_translation(position.extend(0.0)),
+        ))
+        .insert(Spatial {
+            position,
+            rotation: 0.0,
+            radius,
+        })
+        .insert(RigidBody::Dynamic)
+        .insert(Collider::ball(radius))
+        .insert(Velocity::zero())
+        .insert(GravityScale(0.0))
+        .insert(ActiveEvents::COLLISION_EVENTS)
+        .insert(BoundaryWrap)
+        .insert(Hostile)
+        .insert(Weapon::new(Duration::from_millis(500)))
+        .insert(Scripted { handle });
+}
+
+/// Drives every `Scripted` entity's behavior each fixed tick by calling its
+/// script's `update` function and applying the result.
+pub(crate) fn scripted_behavior_system(
+    assets: Res<ScriptAssets>,
+    window: Res<WindowDescriptor>,
+    ships: Query<&Spatial, With<Ship>>,
+    mut asteroid_spawn: EventWriter<AsteroidSpawnEvent>,
+    mut query: Query<(&Spatial, &mut Velocity, &Scripted, Option<&mut Weapon>)>,
+) {
+    for (spatial, mut velocity, scripted, weapon) in query.iter_mut() {
+        let nearest_ship = ships
+            .iter()
+            .map(|ship| ship.position)
+            .min_by(|a, b| {
+                (*a - spatial.position)
+                    .length_squared()
+                    .partial_cmp(&(*b - spatial.position).length_squared())
+                    .unwrap()
+            });
+
+        let api = ScriptApi {
+            position: spatial.position,
+            rotation: spatial.rotation as f64,
+            velocity: velocity.linvel,
+            angvel: velocity.angvel as f64,
+            nearest_ship,
+            window: Vec2::new(window.width, window.height),
+            ..Default::default()
+        };
+
+        let api = match assets.update(scripted.handle, api) {
+            Ok(api) => api,
+            Err(err) => {
+                warn!("script {:?} failed: {}", scripted.handle, err);
+                continue;
+            }
+        };
+
+        if let Some(target) = api.thrust_target {
+            let dir = (target - spatial.position).normalize_or_zero();
+            velocity.linvel += dir * 1.5;
+        }
+
+        if api.fire {
+            if let Some(mut weapon) = weapon {
+                weapon.triggered = true;
+            }
+        }
+
+        for (position, radius) in api.spawns {
+            asteroid_spawn.send(AsteroidSpawnEvent {
+                spatial: Spatial {
+                    position,
+                    radius,
+                    ..Default::default()
+                },
+                inherited_velocity: None,
+            });
+        }
+    }
+}
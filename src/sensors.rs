@@ -0,0 +1,44 @@
+//! Cheap distance-to-nearest-asteroid perception for ships, usable by a
+//! human player's HUD or an `AiPilot` brain alike.
+
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use crate::{Asteroid, Spatial};
+
+/// Rays are cast in a full ring around the ship's facing, `PI / 4` apart.
+pub(crate) const N_RAYS: usize = 8;
+
+/// Normalized (by screen diagonal) nearest-asteroid distance per ray; `1.0`
+/// means nothing was hit.
+#[derive(Debug, Component, Default)]
+pub(crate) struct RaycastSensors(pub(crate) Vec<f32>);
+
+pub(crate) fn raycast_sensor_system(
+    window: Res<WindowDescriptor>,
+    asteroids: Query<&Spatial, With<Asteroid>>,
+    mut ships: Query<(&Spatial, &mut RaycastSensors)>,
+) {
+    let diagonal = (window.width.powi(2) + window.height.powi(2)).sqrt();
+
+    for (spatial, mut sensors) in ships.iter_mut() {
+        sensors.0 = (0..N_RAYS)
+            .map(|i| {
+                let angle = spatial.rotation + PI / 4.0 * i as f32;
+                let ray_dir = Vec2::new(angle.cos(), angle.sin());
+
+                let mut nearest = diagonal;
+                for asteroid in asteroids.iter() {
+                    let v = asteroid.position - spatial.position;
+                    let cross = v.perp_dot(ray_dir);
+                    let dot = v.dot(ray_dir);
+                    if dot >= 0.0 && cross.abs() <= asteroid.radius {
+                        nearest = nearest.min(dot.max(0.0));
+                    }
+                }
+                (nearest / diagonal).min(1.0)
+            })
+            .collect();
+    }
+}